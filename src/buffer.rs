@@ -0,0 +1,57 @@
+//! Fixed, power-of-two ring buffer sizes for the [`lifo`](crate::lifo) and
+//! [`fifo`](crate::fifo) deques.
+
+use std::cell::UnsafeCell;
+use std::mem::MaybeUninit;
+
+mod private {
+    pub trait Sealed {}
+}
+
+/// A fixed-capacity backing store for a work-stealing deque.
+///
+/// This trait is sealed: it is implemented only by the `B2`..`B1024` marker
+/// types re-exported at the crate root, each of which pins the ring buffer to
+/// a specific power-of-two capacity so that index wrapping can be done with a
+/// cheap bitmask rather than a division.
+pub trait Buffer<T>: private::Sealed {
+    #[doc(hidden)]
+    type Array: AsRef<[UnsafeCell<MaybeUninit<T>>]> + AsMut<[UnsafeCell<MaybeUninit<T>>]>;
+
+    /// The number of slots in the ring buffer.
+    const CAPACITY: usize;
+
+    #[doc(hidden)]
+    fn new_array() -> Self::Array;
+}
+
+macro_rules! buffer_size {
+    ($name:ident, $cap:expr, $doc:literal) => {
+        #[doc = $doc]
+        #[derive(Debug)]
+        pub struct $name(());
+
+        impl private::Sealed for $name {}
+
+        impl<T> Buffer<T> for $name {
+            type Array = [UnsafeCell<MaybeUninit<T>>; $cap];
+
+            const CAPACITY: usize = $cap;
+
+            fn new_array() -> Self::Array {
+                std::array::from_fn(|_| UnsafeCell::new(MaybeUninit::uninit()))
+            }
+        }
+    };
+}
+
+buffer_size!(B2, 2, "A ring buffer with a capacity of 2 items.");
+buffer_size!(B4, 4, "A ring buffer with a capacity of 4 items.");
+buffer_size!(B8, 8, "A ring buffer with a capacity of 8 items.");
+buffer_size!(B16, 16, "A ring buffer with a capacity of 16 items.");
+buffer_size!(B32, 32, "A ring buffer with a capacity of 32 items.");
+buffer_size!(B64, 64, "A ring buffer with a capacity of 64 items.");
+buffer_size!(B128, 128, "A ring buffer with a capacity of 128 items.");
+buffer_size!(B256, 256, "A ring buffer with a capacity of 256 items.");
+buffer_size!(B512, 512, "A ring buffer with a capacity of 512 items.");
+buffer_size!(B1024, 1024, "A ring buffer with a capacity of 1024 items.");
@@ -0,0 +1,384 @@
+//! An unbounded, multi-producer multi-consumer overflow queue shared by all
+//! threads in a work-stealing scheduler.
+//!
+//! An [`Injector`] plays the same role as the injector queue in other task
+//! schedulers: workers spill excess work into it when their own deque is
+//! full, and newly idle workers refill from it in bulk via
+//! [`Injector::steal_batch_and_pop`]. Internally it is a singly linked list
+//! of fixed-size blocks — reusing the [`Buffer`] marker types for block
+//! length — so it never needs an upper bound on the number of items it can
+//! hold.
+//!
+//! A block fully drained by `steal_batch_and_pop` is unlinked from the head
+//! of the list, but a concurrent call on another thread may still be mid-way
+//! through reading out of it. Reclaiming drained blocks therefore uses the
+//! same generation/epoch scheme as [`lifo::growable`](crate::lifo::growable):
+//! every call announces the generation it observed before touching a block,
+//! and a block is only freed once no announced generation is old enough to
+//! still be looking at it.
+
+use std::cell::RefCell;
+use std::fmt;
+use std::ptr;
+use std::sync::atomic::{fence, AtomicPtr, AtomicU64, AtomicU8, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::lifo::Worker;
+use crate::{Buffer, StealError};
+
+/// A slot has not yet been claimed by a producer.
+const EMPTY: u8 = 0;
+/// A producer has claimed the slot and is writing to it.
+const WRITING: u8 = 1;
+/// The slot holds a value ready to be read.
+const READY: u8 = 2;
+
+/// Sentinel meaning "this call is not currently observing any block".
+const NO_EPOCH: usize = usize::MAX;
+
+struct Block<T, B: Buffer<T>> {
+    values: B::Array,
+    states: Box<[AtomicU8]>,
+    /// Next slot index to claim for writing. Not clamped to the block's
+    /// capacity: producers that overshoot it help install `next` instead.
+    write: AtomicUsize,
+    /// Next slot index to claim for reading. Only advanced by CAS, and never
+    /// past `write` or the block's capacity.
+    read: AtomicUsize,
+    next: AtomicPtr<Block<T, B>>,
+}
+
+impl<T, B: Buffer<T>> Block<T, B> {
+    fn new() -> Box<Self> {
+        Box::new(Block {
+            values: B::new_array(),
+            states: (0..B::CAPACITY).map(|_| AtomicU8::new(EMPTY)).collect(),
+            write: AtomicUsize::new(0),
+            read: AtomicUsize::new(0),
+            next: AtomicPtr::new(ptr::null_mut()),
+        })
+    }
+
+    unsafe fn write_value(&self, idx: usize, value: T) {
+        self.states[idx].store(WRITING, Ordering::Relaxed);
+        (*self.values.as_ref()[idx].get()).write(value);
+        self.states[idx].store(READY, Ordering::Release);
+    }
+
+    unsafe fn read_value(&self, idx: usize) -> T {
+        (*self.values.as_ref()[idx].get()).assume_init_read()
+    }
+}
+
+struct Shared<T, B: Buffer<T>> {
+    head: AtomicPtr<Block<T, B>>,
+    tail: AtomicPtr<Block<T, B>>,
+    /// Identifies this queue to the thread-local epoch cache below, immune
+    /// to address reuse once a queue is dropped (unlike the `Shared`'s own
+    /// address).
+    id: u64,
+    /// Bumped every time a drained block is unlinked from `head`.
+    generation: AtomicUsize,
+    /// One slot per thread that has called `steal_batch_and_pop`, recording
+    /// the generation its current call is observing (or `NO_EPOCH` between
+    /// calls).
+    epochs: Mutex<Vec<Arc<AtomicUsize>>>,
+    /// Blocks unlinked from the head chain, tagged with the generation they
+    /// were retired at, awaiting reclaim.
+    retired: Mutex<Vec<(usize, *mut Block<T, B>)>>,
+}
+
+unsafe impl<T: Send, B: Buffer<T>> Send for Shared<T, B> {}
+unsafe impl<T: Send, B: Buffer<T>> Sync for Shared<T, B> {}
+
+fn next_injector_id() -> u64 {
+    static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+thread_local! {
+    /// This thread's epoch cell for each `Injector` it has called into,
+    /// reused across calls instead of registering with `epochs` every time.
+    static EPOCH_CELLS: RefCell<Vec<(u64, Arc<AtomicUsize>)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// An unbounded MPMC queue used as a central overflow for a pool of
+/// [`lifo::Worker`](crate::lifo::Worker)s.
+///
+/// `B` fixes the size of each internal block (not the queue's overall
+/// capacity, which is unbounded) and can be any of the marker types `B2`,
+/// `B4`, .. `B1024`.
+pub struct Injector<T, B: Buffer<T>> {
+    shared: Arc<Shared<T, B>>,
+}
+
+impl<T, B: Buffer<T>> Injector<T, B> {
+    /// Creates a new, empty injector queue.
+    pub fn new() -> Self {
+        let block = Box::into_raw(Block::new());
+        Injector {
+            shared: Arc::new(Shared {
+                head: AtomicPtr::new(block),
+                tail: AtomicPtr::new(block),
+                id: next_injector_id(),
+                generation: AtomicUsize::new(0),
+                epochs: Mutex::new(Vec::new()),
+                retired: Mutex::new(Vec::new()),
+            }),
+        }
+    }
+
+    /// This thread's epoch cell for this queue, registering a new one in
+    /// `shared.epochs` the first time this thread calls into it.
+    fn epoch_cell(&self) -> Arc<AtomicUsize> {
+        EPOCH_CELLS.with(|cells| {
+            let mut cells = cells.borrow_mut();
+            if let Some((_, cell)) = cells.iter().find(|(id, _)| *id == self.shared.id) {
+                return cell.clone();
+            }
+            let cell = Arc::new(AtomicUsize::new(NO_EPOCH));
+            self.shared.epochs.lock().unwrap().push(cell.clone());
+            cells.push((self.shared.id, cell.clone()));
+            cell
+        })
+    }
+
+    /// Retires a drained block that has already been unlinked from the head
+    /// chain, freeing it once no announced generation is old enough to still
+    /// be reading it.
+    fn retire(&self, old_ptr: *mut Block<T, B>) {
+        let retiring_generation = self.shared.generation.fetch_add(1, Ordering::AcqRel);
+        self.shared
+            .retired
+            .lock()
+            .unwrap()
+            .push((retiring_generation, old_ptr));
+        self.reclaim();
+    }
+
+    /// Frees every retired block older than the oldest generation any live
+    /// call to `steal_batch_and_pop` is currently observing.
+    fn reclaim(&self) {
+        // Pairs with a caller's own fence between announcing its epoch and
+        // reading `generation` (see `steal_batch_and_pop`): plain
+        // Release/Acquire on each side's atomic can't rule out both sides
+        // missing each other's write, since they're two independent
+        // orderings. Only a shared SeqCst total order guarantees that if a
+        // caller's epoch announcement isn't visible here yet, it will in
+        // turn see this generation bump.
+        fence(Ordering::SeqCst);
+        let min_observed = {
+            let epochs = self.shared.epochs.lock().unwrap();
+            epochs
+                .iter()
+                .map(|epoch| epoch.load(Ordering::Acquire))
+                .filter(|&generation| generation != NO_EPOCH)
+                .min()
+        };
+        let mut retired = self.shared.retired.lock().unwrap();
+        retired.retain(|&(generation, ptr)| {
+            if min_observed.is_some_and(|oldest| generation >= oldest) {
+                true
+            } else {
+                // SAFETY: no call references generation `generation` (or
+                // anything older) anymore, so we have sole ownership.
+                unsafe { drop(Box::from_raw(ptr)) };
+                false
+            }
+        });
+    }
+
+    /// Appends an item to the queue. Never fails: a new block is allocated
+    /// once the current one fills up.
+    pub fn push(&self, value: T) {
+        let mut value = value;
+        loop {
+            let tail_ptr = self.shared.tail.load(Ordering::Acquire);
+            let tail = unsafe { &*tail_ptr };
+            let idx = tail.write.fetch_add(1, Ordering::Relaxed);
+            if idx < B::CAPACITY {
+                unsafe { tail.write_value(idx, value) };
+                return;
+            }
+            value = self.push_retry(tail_ptr, tail, value);
+        }
+    }
+
+    /// Installs (or helps install) the block following `tail`, advancing the
+    /// shared tail pointer, and hands the still-unwritten `value` back for
+    /// another attempt.
+    fn push_retry(&self, tail_ptr: *mut Block<T, B>, tail: &Block<T, B>, value: T) -> T {
+        let next_ptr = tail.next.load(Ordering::Acquire);
+        let next_ptr = if next_ptr.is_null() {
+            let new_block = Box::into_raw(Block::new());
+            match tail.next.compare_exchange(
+                ptr::null_mut(),
+                new_block,
+                Ordering::Release,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => new_block,
+                Err(actual) => {
+                    // Lost the race to link a block: drop ours, use theirs.
+                    unsafe { drop(Box::from_raw(new_block)) };
+                    actual
+                }
+            }
+        } else {
+            next_ptr
+        };
+        let _ = self.shared.tail.compare_exchange(
+            tail_ptr,
+            next_ptr,
+            Ordering::Release,
+            Ordering::Relaxed,
+        );
+        value
+    }
+
+    /// Claims up to `count(available_hint)` contiguous items from the head
+    /// of the queue, moving all but one into `dest`'s ring buffer (clamped
+    /// to its spare capacity) and returning the remaining item directly
+    /// along with the number moved — mirroring
+    /// [`Stealer::steal_and_pop`](crate::lifo::Stealer::steal_and_pop).
+    ///
+    /// `available_hint` is the number of slots in the current block that
+    /// producers have claimed and no consumer has yet (some may still be
+    /// mid-write), not the size of the whole queue.
+    pub fn steal_batch_and_pop<DB: Buffer<T>>(
+        &self,
+        dest: &Worker<T, DB>,
+        count: impl FnOnce(usize) -> usize,
+    ) -> Result<(T, usize), StealError> {
+        // Announce the generation we're about to look at before touching any
+        // block, so a concurrent call that drains and retires it knows to
+        // keep it alive until we're done. See the module docs.
+        let epoch_cell = self.epoch_cell();
+        let generation = self.shared.generation.load(Ordering::Acquire);
+        epoch_cell.store(generation, Ordering::Release);
+        fence(Ordering::SeqCst);
+        let result = self.try_steal_batch_and_pop(dest, count);
+        epoch_cell.store(NO_EPOCH, Ordering::Release);
+        result
+    }
+
+    fn try_steal_batch_and_pop<DB: Buffer<T>>(
+        &self,
+        dest: &Worker<T, DB>,
+        count: impl FnOnce(usize) -> usize,
+    ) -> Result<(T, usize), StealError> {
+        let head_ptr = self.shared.head.load(Ordering::Acquire);
+        let head = unsafe { &*head_ptr };
+
+        let start = head.read.load(Ordering::Relaxed);
+        let write = head.write.load(Ordering::Acquire);
+        if start >= write.min(B::CAPACITY) {
+            // This block has nothing more ready; advance to the next one if
+            // it exists, otherwise the queue is genuinely empty.
+            let next_ptr = head.next.load(Ordering::Acquire);
+            if next_ptr.is_null() {
+                return Err(StealError::Empty);
+            }
+            if start < B::CAPACITY {
+                // `next` is only linked once this block's slots are all
+                // claimed, so the `write` read above was stale: slots from
+                // `start` on are still waiting to be read.
+                return Err(StealError::Busy);
+            }
+            if self
+                .shared
+                .head
+                .compare_exchange(head_ptr, next_ptr, Ordering::Release, Ordering::Relaxed)
+                .is_ok()
+            {
+                self.retire(head_ptr);
+            }
+            return Err(StealError::Busy);
+        }
+
+        let available = write.min(B::CAPACITY) - start;
+        let n = count(available)
+            .max(1)
+            .min(available)
+            .min(1 + dest.spare_capacity());
+
+        // Claim the run with a CAS rather than an add-then-maybe-subtract:
+        // the latter would let another consumer observe the transient
+        // over-advanced `read` in between and wrongly conclude the block is
+        // drained, losing work it never actually got to read.
+        if head
+            .read
+            .compare_exchange(start, start + n, Ordering::SeqCst, Ordering::Relaxed)
+            .is_err()
+        {
+            // Lost the race for this run to another consumer.
+            return Err(StealError::Busy);
+        }
+
+        // We won the claim on this whole run, so the producers writing to
+        // it are already committed to finishing. The `n - 1` slots bound for
+        // `dest` are staged in a stack buffer sized like `dest`'s own ring
+        // buffer (always big enough, since `n - 1 <= dest.spare_capacity()`)
+        // instead of a heap-allocated `Vec`; the last slot is kept in a
+        // plain local.
+        let staging = DB::new_array();
+        for i in 0..n - 1 {
+            let idx = start + i;
+            while head.states[idx].load(Ordering::Acquire) != READY {
+                std::hint::spin_loop();
+            }
+            unsafe { (*staging.as_ref()[i].get()).write(head.read_value(idx)) };
+        }
+        let last_idx = start + n - 1;
+        while head.states[last_idx].load(Ordering::Acquire) != READY {
+            std::hint::spin_loop();
+        }
+        let last = unsafe { head.read_value(last_idx) };
+
+        for i in 0..n - 1 {
+            let value = unsafe { (*staging.as_ref()[i].get()).assume_init_read() };
+            dest.push(value).ok().expect("reserved spare capacity");
+        }
+        Ok((last, n - 1))
+    }
+}
+
+impl<T, B: Buffer<T>> fmt::Debug for Injector<T, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Injector").finish()
+    }
+}
+
+impl<T, B: Buffer<T>> Default for Injector<T, B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, B: Buffer<T>> Drop for Injector<T, B> {
+    fn drop(&mut self) {
+        // No call to `steal_batch_and_pop` can still be running (it borrows
+        // `&self`), so every retired block is safe to free unconditionally.
+        for &(_, ptr) in self.shared.retired.lock().unwrap().iter() {
+            unsafe { drop(Box::from_raw(ptr)) };
+        }
+
+        let mut block_ptr = self.shared.head.load(Ordering::Relaxed);
+        loop {
+            let block = unsafe { Box::from_raw(block_ptr) };
+            let read = block.read.load(Ordering::Relaxed);
+            let write = block.write.load(Ordering::Relaxed).min(B::CAPACITY);
+            for idx in read..write {
+                if block.states[idx].load(Ordering::Relaxed) == READY {
+                    unsafe { drop(block.read_value(idx)) };
+                }
+            }
+            let next = block.next.load(Ordering::Relaxed);
+            drop(block);
+            if next.is_null() {
+                break;
+            }
+            block_ptr = next;
+        }
+    }
+}
@@ -0,0 +1,78 @@
+//! # st3
+//!
+//! A lock-free, single-producer, multi-consumer work-stealing deque.
+//!
+//! st3 provides the building blocks used by a work-stealing scheduler: each
+//! thread owns a [`Worker`](lifo::Worker) that it pushes to and pops from
+//! without contention, and hands out [`Stealer`](lifo::Stealer) handles that
+//! other threads can use to steal work when they run out of their own.
+//!
+//! Two flavors are provided, differing only in the order in which the owning
+//! thread pops items relative to the order it pushed them:
+//!
+//! * [`lifo`] — the owner pops the most recently pushed item first (stack
+//!   order), which favors cache locality for divide-and-conquer workloads.
+//! * [`fifo`] — the owner pops the least recently pushed item first (queue
+//!   order), which favors fairness between tasks.
+//!
+//! In both flavors, stealers always take items in FIFO order from the
+//! opposite end of the deque.
+//!
+//! Ring buffer capacities are fixed at compile time via the [`Buffer`] marker
+//! types (`B2`..`B1024`). [`injector::Injector`] provides an unbounded,
+//! multi-producer multi-consumer overflow queue that a pool of workers can
+//! share to spill excess work and refill from in bulk.
+//!
+//! The `scheduler` feature adds [`scheduler`], a small fork/join thread pool
+//! built on top of these primitives.
+
+#![warn(missing_docs)]
+
+mod buffer;
+pub mod fifo;
+pub mod injector;
+pub mod lifo;
+mod raw;
+#[cfg(feature = "scheduler")]
+pub mod scheduler;
+
+pub use buffer::{Buffer, B1024, B128, B16, B2, B256, B32, B4, B512, B64, B8};
+
+use std::error::Error;
+use std::fmt;
+
+/// Error returned by a failed steal operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StealError {
+    /// The deque being stolen from was empty.
+    Empty,
+    /// The steal lost a race with a concurrent operation (a pop, another
+    /// steal, or a buffer resize) and should be retried.
+    Busy,
+}
+
+impl fmt::Display for StealError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StealError::Empty => write!(f, "the deque is empty"),
+            StealError::Busy => write!(f, "lost race with a concurrent operation"),
+        }
+    }
+}
+
+impl Error for StealError {}
+
+/// Error returned when pushing an item onto a deque that is already at
+/// capacity.
+///
+/// The item that could not be pushed is returned so that it isn't lost.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PushError<T>(pub T);
+
+impl<T> fmt::Display for PushError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the deque is at capacity")
+    }
+}
+
+impl<T: fmt::Debug> Error for PushError<T> {}
@@ -0,0 +1,499 @@
+//! An opt-in growable variant of [`lifo::Worker`](super::Worker) whose
+//! [`push`](Worker::push) and [`extend`](Worker::extend) never fail: the
+//! ring buffer is reallocated at double its capacity instead of rejecting
+//! new items once full.
+//!
+//! Concurrent stealers may still be reading the buffer that a resize is
+//! replacing, so the old allocation cannot simply be freed in place. Instead,
+//! each resize bumps a generation counter; a stealer records the generation
+//! it observes for the duration of a steal, and the owner only reclaims a
+//! retired buffer once no stealer is observing its generation or an older
+//! one.
+
+use std::cell::{Cell, UnsafeCell};
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem::MaybeUninit;
+use std::sync::atomic::{fence, AtomicPtr, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::StealError;
+
+/// Sentinel meaning "this stealer is not currently observing any buffer".
+const NO_EPOCH: usize = usize::MAX;
+
+/// A heap-allocated, power-of-two ring buffer: one generation of a
+/// [`Worker`]'s backing storage.
+struct DynBuf<T> {
+    mask: usize,
+    slots: Box<[UnsafeCell<MaybeUninit<T>>]>,
+}
+
+impl<T> DynBuf<T> {
+    fn new(capacity: usize) -> Self {
+        debug_assert!(capacity.is_power_of_two());
+        DynBuf {
+            mask: capacity - 1,
+            slots: (0..capacity)
+                .map(|_| UnsafeCell::new(MaybeUninit::uninit()))
+                .collect(),
+        }
+    }
+
+    fn capacity(&self) -> usize {
+        self.mask + 1
+    }
+
+    unsafe fn write(&self, idx: usize, value: T) {
+        (*self.slots[idx & self.mask].get()).write(value);
+    }
+
+    unsafe fn read(&self, idx: usize) -> T {
+        (*self.slots[idx & self.mask].get()).assume_init_read()
+    }
+}
+
+struct Shared<T> {
+    top: AtomicUsize,
+    bottom: AtomicUsize,
+    buffer: AtomicPtr<DynBuf<T>>,
+    generation: AtomicUsize,
+    /// One slot per live `Stealer`, recording the generation it is currently
+    /// observing (or `NO_EPOCH` between steals).
+    epochs: Mutex<Vec<Arc<AtomicUsize>>>,
+    /// Buffers superseded by a resize, tagged with the generation they were
+    /// retired at. Only ever touched by the owning thread.
+    retired: UnsafeCell<Vec<(usize, *mut DynBuf<T>)>>,
+}
+
+unsafe impl<T: Send> Send for Shared<T> {}
+unsafe impl<T: Send> Sync for Shared<T> {}
+
+/// The single-producer end of a growable LIFO work-stealing deque.
+///
+/// Unlike [`lifo::Worker`](super::Worker), [`push`](Worker::push) and
+/// [`extend`](Worker::extend) never fail: the buffer is reallocated at
+/// double its capacity whenever it fills up.
+pub struct Worker<T> {
+    shared: Arc<Shared<T>>,
+    // See `lifo::Worker`: `Send`, but deliberately not `Sync`.
+    _not_sync: PhantomData<Cell<()>>,
+}
+
+impl<T> Worker<T> {
+    /// Creates a new, empty growable worker deque with an initial capacity
+    /// taken from the fixed-size marker `B`.
+    pub fn new<B: crate::Buffer<T>>() -> Self {
+        let buffer = Box::into_raw(Box::new(DynBuf::new(B::CAPACITY)));
+        Worker {
+            shared: Arc::new(Shared {
+                top: AtomicUsize::new(0),
+                bottom: AtomicUsize::new(0),
+                buffer: AtomicPtr::new(buffer),
+                generation: AtomicUsize::new(0),
+                epochs: Mutex::new(Vec::new()),
+                retired: UnsafeCell::new(Vec::new()),
+            }),
+            _not_sync: PhantomData,
+        }
+    }
+
+    /// Creates a stealer handle that other threads can use to steal from this
+    /// deque.
+    pub fn stealer(&self) -> Stealer<T> {
+        Stealer::new(self.shared.clone())
+    }
+
+    /// Pushes an item. This never fails: the buffer grows instead of
+    /// rejecting the push.
+    pub fn push(&self, value: T) {
+        let bottom = self.shared.bottom.load(Ordering::Relaxed);
+        let top = self.shared.top.load(Ordering::Acquire);
+        let buf_ptr = self.shared.buffer.load(Ordering::Relaxed);
+        let buf = unsafe { &*buf_ptr };
+        let buf = if bottom.wrapping_sub(top) >= buf.capacity() {
+            self.grow(buf_ptr, top, bottom)
+        } else {
+            buf
+        };
+        unsafe { buf.write(bottom, value) };
+        self.shared
+            .bottom
+            .store(bottom.wrapping_add(1), Ordering::Release);
+    }
+
+    /// Pushes every item from `iter`. This never fails: the buffer grows as
+    /// needed to fit all of them.
+    pub fn extend<I: IntoIterator<Item = T>>(&self, iter: I) {
+        for value in iter {
+            self.push(value);
+        }
+    }
+
+    /// Pops the most recently pushed item, or `None` if the deque is empty.
+    pub fn pop(&self) -> Option<T> {
+        let bottom = self.shared.bottom.load(Ordering::Relaxed);
+        let new_bottom = bottom.wrapping_sub(1);
+        self.shared.bottom.store(new_bottom, Ordering::Relaxed);
+        fence(Ordering::SeqCst);
+
+        let top = self.shared.top.load(Ordering::Relaxed);
+        let size = new_bottom.wrapping_sub(top) as isize;
+        if size < 0 {
+            self.shared.bottom.store(bottom, Ordering::Relaxed);
+            return None;
+        }
+
+        // SAFETY: only the owner replaces `buffer`, so a relaxed load on the
+        // owning thread always sees the current generation.
+        let buf = unsafe { &*self.shared.buffer.load(Ordering::Relaxed) };
+        let value = unsafe { buf.read(new_bottom) };
+        if size == 0 {
+            let won = self
+                .shared
+                .top
+                .compare_exchange(
+                    top,
+                    top.wrapping_add(1),
+                    Ordering::SeqCst,
+                    Ordering::Relaxed,
+                )
+                .is_ok();
+            self.shared.bottom.store(bottom, Ordering::Relaxed);
+            if !won {
+                // Lost the race: a stealer already claimed this slot and
+                // will return this same item, so don't drop our copy of it.
+                std::mem::forget(value);
+                return None;
+            }
+        }
+        Some(value)
+    }
+
+    /// The number of items currently in the deque.
+    pub fn len(&self) -> usize {
+        let bottom = self.shared.bottom.load(Ordering::Relaxed);
+        let top = self.shared.top.load(Ordering::Acquire);
+        bottom.wrapping_sub(top)
+    }
+
+    /// Returns `true` if the deque currently holds no items.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Doubles the buffer's capacity, copies the live `[top, bottom)` range
+    /// into it, publishes it, and retires the old allocation for deferred
+    /// reclamation. Returns a reference to the new buffer.
+    fn grow(&self, old_ptr: *mut DynBuf<T>, top: usize, bottom: usize) -> &DynBuf<T> {
+        let old = unsafe { &*old_ptr };
+
+        // Claim the entire `[top, bottom)` range before copying it: a plain
+        // re-read of `top` isn't enough, since a stealer could still claim
+        // (CAS) and read an index in that range while the copy below is
+        // running, producing two live copies of the same value. CASing
+        // `top` up to `bottom` first means any such stealer's own CAS is
+        // guaranteed to lose and forget its duplicate read (see
+        // `try_steal_one`/`try_steal_many`), same as it would against a
+        // racing `pop`.
+        let mut claimed_top = top;
+        while claimed_top != bottom {
+            match self.shared.top.compare_exchange(
+                claimed_top,
+                bottom,
+                Ordering::SeqCst,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => break,
+                Err(actual) => claimed_top = actual,
+            }
+        }
+
+        let new_buf = DynBuf::new(old.capacity() * 2);
+        for i in claimed_top..bottom {
+            unsafe { new_buf.write(i, old.read(i)) };
+        }
+        let new_ptr = Box::into_raw(Box::new(new_buf));
+        self.shared.buffer.store(new_ptr, Ordering::Release);
+
+        // Hand the range back to stealers now that the new buffer backing
+        // it is published.
+        self.shared.top.store(claimed_top, Ordering::Release);
+
+        let retiring_generation = self.shared.generation.fetch_add(1, Ordering::AcqRel);
+        // SAFETY: `retired` is only ever touched by the owning thread.
+        unsafe { (*self.shared.retired.get()).push((retiring_generation, old_ptr)) };
+        self.reclaim();
+
+        unsafe { &*new_ptr }
+    }
+
+    /// Frees every retired buffer older than the oldest generation any live
+    /// steal is currently observing.
+    fn reclaim(&self) {
+        // This and a stealer's own fence between announcing its epoch and
+        // reading `generation` (see `steal`/`steal_and_pop`) are a Dekker
+        // pair: plain Release/Acquire on each side's atomic isn't enough to
+        // rule out both sides missing each other's write, since they're two
+        // independent orderings. Only a shared SeqCst total order guarantees
+        // that if a stealer's epoch announcement isn't visible here yet, it
+        // will in turn see this generation bump, so the `retain` below can't
+        // race a steal that's still using the retiring buffer.
+        fence(Ordering::SeqCst);
+        let min_observed = {
+            let epochs = self.shared.epochs.lock().unwrap();
+            epochs
+                .iter()
+                .map(|epoch| epoch.load(Ordering::Acquire))
+                .filter(|&generation| generation != NO_EPOCH)
+                .min()
+        };
+
+        // SAFETY: `retired` is only ever touched by the owning thread.
+        let retired = unsafe { &mut *self.shared.retired.get() };
+        retired.retain(|&(generation, ptr)| {
+            if min_observed.is_some_and(|oldest| generation >= oldest) {
+                true
+            } else {
+                // SAFETY: no steal references generation `generation` (or
+                // anything older) anymore, so we have sole ownership.
+                unsafe { drop(Box::from_raw(ptr)) };
+                false
+            }
+        });
+    }
+}
+
+impl<T> Default for Worker<T> {
+    /// Creates a new, empty growable worker deque, starting at the smallest
+    /// fixed-size capacity (it grows from there as needed).
+    fn default() -> Self {
+        Self::new::<crate::B2>()
+    }
+}
+
+impl<T> fmt::Debug for Worker<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Worker").field("len", &self.len()).finish()
+    }
+}
+
+impl<T> Drop for Shared<T> {
+    fn drop(&mut self) {
+        let mut top = self.top.load(Ordering::Relaxed);
+        let bottom = self.bottom.load(Ordering::Relaxed);
+        let buf_ptr = self.buffer.load(Ordering::Relaxed);
+        let buf = unsafe { &*buf_ptr };
+        while top != bottom {
+            unsafe { drop(buf.read(top)) };
+            top = top.wrapping_add(1);
+        }
+        unsafe { drop(Box::from_raw(buf_ptr)) };
+        // This only runs once the last `Arc<Shared<T>>` - held by the
+        // `Worker` and every outstanding `Stealer` clone - goes away, so no
+        // in-flight steal can still be observing a retired generation.
+        for &(_, ptr) in unsafe { &*self.retired.get() } {
+            unsafe { drop(Box::from_raw(ptr)) };
+        }
+    }
+}
+
+/// A handle that lets another thread steal items from a growable [`Worker`]'s
+/// deque.
+///
+/// Each `Stealer` owns one slot in `Shared::epochs` and assumes at most one
+/// steal is in flight through it at a time, so it is deliberately not
+/// `Sync`: share it across threads by `clone()`-ing one per thread (as
+/// `lifo::Stealer` and `fifo::Stealer` also expect), not by sharing a single
+/// instance.
+pub struct Stealer<T> {
+    shared: Arc<Shared<T>>,
+    epoch: Arc<AtomicUsize>,
+    // `Cell` is `Send` but not `Sync`: see above.
+    _not_sync: PhantomData<Cell<()>>,
+}
+
+impl<T> Stealer<T> {
+    fn new(shared: Arc<Shared<T>>) -> Self {
+        let epoch = Arc::new(AtomicUsize::new(NO_EPOCH));
+        shared.epochs.lock().unwrap().push(epoch.clone());
+        Stealer {
+            shared,
+            epoch,
+            _not_sync: PhantomData,
+        }
+    }
+
+    /// Steals a single item, or an error if the deque is empty or the steal
+    /// lost a race with a concurrent pop, steal, or resize.
+    pub fn steal(&self) -> Result<T, StealError> {
+        let generation = self.shared.generation.load(Ordering::Acquire);
+        self.epoch.store(generation, Ordering::Release);
+        fence(Ordering::SeqCst);
+        let result = self.try_steal_one(generation);
+        self.epoch.store(NO_EPOCH, Ordering::Release);
+        result
+    }
+
+    fn try_steal_one(&self, announced_generation: usize) -> Result<T, StealError> {
+        // Validate `buf_ptr` against the generation we announced *before*
+        // reading `top`: `top` is only ever a valid index into whichever
+        // buffer is current as of this check, so re-reading it afterwards
+        // (rather than reusing a value read beforehand) is what guarantees
+        // `buf.read(top)` below lands inside the range `grow()` actually
+        // copied into this buffer. A `top` read before this check could
+        // predate a resize that already retargeted it at a different
+        // generation's buffer, landing on a slot that generation's copy
+        // never populated.
+        let buf_ptr = self.shared.buffer.load(Ordering::Acquire);
+        if self.shared.generation.load(Ordering::Acquire) != announced_generation {
+            return Err(StealError::Busy);
+        }
+        let buf = unsafe { &*buf_ptr };
+
+        let top = self.shared.top.load(Ordering::Acquire);
+        fence(Ordering::SeqCst);
+        let bottom = self.shared.bottom.load(Ordering::Acquire);
+        if bottom.wrapping_sub(top) as isize <= 0 {
+            return Err(StealError::Empty);
+        }
+
+        let value = unsafe { buf.read(top) };
+        if self
+            .shared
+            .top
+            .compare_exchange(
+                top,
+                top.wrapping_add(1),
+                Ordering::SeqCst,
+                Ordering::Relaxed,
+            )
+            .is_ok()
+        {
+            Ok(value)
+        } else {
+            // Lost the race: this was a duplicate read of a slot the winner
+            // still logically owns, so don't drop it here.
+            std::mem::forget(value);
+            Err(StealError::Busy)
+        }
+    }
+
+    /// Steals a batch of items, moving all but one of them into `dest` and
+    /// returning the last one directly along with the number moved.
+    pub fn steal_and_pop<DB: crate::Buffer<T>>(
+        &self,
+        dest: &crate::lifo::Worker<T, DB>,
+        count: impl FnOnce(usize) -> usize,
+    ) -> Result<(T, usize), StealError> {
+        let generation = self.shared.generation.load(Ordering::Acquire);
+        self.epoch.store(generation, Ordering::Release);
+        fence(Ordering::SeqCst);
+        let result = self.try_steal_many(generation, dest, count);
+        self.epoch.store(NO_EPOCH, Ordering::Release);
+        result
+    }
+
+    fn try_steal_many<DB: crate::Buffer<T>>(
+        &self,
+        announced_generation: usize,
+        dest: &crate::lifo::Worker<T, DB>,
+        count: impl FnOnce(usize) -> usize,
+    ) -> Result<(T, usize), StealError> {
+        // Validate `buf_ptr` against the generation we announced *before*
+        // reading `top`: see `try_steal_one` for why re-reading `top`
+        // afterwards (rather than reusing a value read beforehand) is what
+        // guarantees the range read below lands inside what `grow()` copied
+        // into this buffer.
+        let buf_ptr = self.shared.buffer.load(Ordering::Acquire);
+        if self.shared.generation.load(Ordering::Acquire) != announced_generation {
+            return Err(StealError::Busy);
+        }
+        let buf = unsafe { &*buf_ptr };
+
+        let top = self.shared.top.load(Ordering::Acquire);
+        fence(Ordering::SeqCst);
+        let bottom = self.shared.bottom.load(Ordering::Acquire);
+        let available = bottom.wrapping_sub(top) as isize;
+        if available <= 0 {
+            return Err(StealError::Empty);
+        }
+
+        let available = available as usize;
+        let n = count(available)
+            .max(1)
+            .min(available)
+            .min(1 + dest.spare_capacity());
+
+        // The `n - 1` items bound for `dest` are staged in a stack buffer
+        // sized like `dest`'s own ring buffer (always big enough, since
+        // `n - 1 <= dest.spare_capacity()`) instead of a heap-allocated
+        // `Vec`; the last item is kept in a plain local.
+        let staging = DB::new_array();
+        for i in 0..n - 1 {
+            unsafe { (*staging.as_ref()[i].get()).write(buf.read(top.wrapping_add(i))) };
+        }
+        let last = unsafe { buf.read(top.wrapping_add(n - 1)) };
+
+        if self
+            .shared
+            .top
+            .compare_exchange(
+                top,
+                top.wrapping_add(n),
+                Ordering::SeqCst,
+                Ordering::Relaxed,
+            )
+            .is_err()
+        {
+            // Lost the race: nothing was ever read out of `staging`, so
+            // there's nothing live in it to drop; `last` is a duplicate read
+            // that must not be dropped either.
+            std::mem::forget(last);
+            return Err(StealError::Busy);
+        }
+
+        for i in 0..n - 1 {
+            let value = unsafe { (*staging.as_ref()[i].get()).assume_init_read() };
+            dest.push(value).ok().expect("reserved spare capacity");
+        }
+        Ok((last, n - 1))
+    }
+
+    /// Upper-bound estimate of the number of items in the deque.
+    pub fn len(&self) -> usize {
+        let top = self.shared.top.load(Ordering::Acquire);
+        let bottom = self.shared.bottom.load(Ordering::Acquire);
+        // A concurrent pop can leave `bottom` momentarily below `top`.
+        (bottom.wrapping_sub(top) as isize).max(0) as usize
+    }
+
+    /// Returns `true` if the deque appeared empty at the time of the call.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T> Clone for Stealer<T> {
+    fn clone(&self) -> Self {
+        Stealer::new(self.shared.clone())
+    }
+}
+
+impl<T> Drop for Stealer<T> {
+    fn drop(&mut self) {
+        // Remove this clone's own slot so `Shared::epochs` doesn't grow
+        // without bound as `Stealer`s are created and dropped over a
+        // program's lifetime.
+        let mut epochs = self.shared.epochs.lock().unwrap();
+        if let Some(pos) = epochs.iter().position(|epoch| Arc::ptr_eq(epoch, &self.epoch)) {
+            epochs.swap_remove(pos);
+        }
+    }
+}
+
+impl<T> fmt::Debug for Stealer<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Stealer").field("len", &self.len()).finish()
+    }
+}
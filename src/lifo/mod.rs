@@ -0,0 +1,172 @@
+//! A work-stealing deque where the owning thread pops the most recently
+//! pushed item first (stack order).
+//!
+//! See the [crate-level documentation](crate) for how this compares to
+//! [`fifo`](crate::fifo).
+
+use std::cell::Cell;
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use crate::raw::RawDeque;
+use crate::{Buffer, PushError, StealError};
+
+pub mod growable;
+
+/// The single-producer end of a LIFO work-stealing deque.
+///
+/// A `Worker` cannot be shared between threads; use [`Worker::stealer`] to
+/// hand out [`Stealer`] handles to other threads instead.
+pub struct Worker<T, B: Buffer<T>> {
+    deque: Arc<RawDeque<T, B>>,
+    // `Cell` is `Send` but not `Sync`: a `Worker` may be moved to another
+    // thread, but never accessed concurrently from two threads at once.
+    _not_sync: PhantomData<Cell<()>>,
+}
+
+impl<T, B: Buffer<T>> Worker<T, B> {
+    /// Creates a new, empty worker deque.
+    pub fn new() -> Self {
+        Worker {
+            deque: Arc::new(RawDeque::new()),
+            _not_sync: PhantomData,
+        }
+    }
+
+    /// Creates a stealer handle that other threads can use to steal from this
+    /// deque.
+    pub fn stealer(&self) -> Stealer<T, B> {
+        Stealer {
+            deque: self.deque.clone(),
+        }
+    }
+
+    /// Pushes an item, returning it back on error if the deque is full.
+    pub fn push(&self, value: T) -> Result<(), PushError<T>> {
+        self.deque.push(value)
+    }
+
+    /// Pushes as many items from `iter` as there is room for, silently
+    /// dropping the rest of the iterator once the deque is full.
+    pub fn extend<I: IntoIterator<Item = T>>(&self, iter: I) {
+        for value in iter {
+            if self.deque.push(value).is_err() {
+                break;
+            }
+        }
+    }
+
+    /// Pops the most recently pushed item, or `None` if the deque is empty.
+    pub fn pop(&self) -> Option<T> {
+        self.deque.pop_bottom()
+    }
+
+    /// Removes up to `count(len())` items from the stealing end of the
+    /// deque and returns them as an iterator, blocking concurrent steals
+    /// until the iterator is exhausted or dropped.
+    ///
+    /// Returns `None` if the deque is empty or `count` requests zero items.
+    pub fn drain(&self, count: impl FnOnce(usize) -> usize) -> Option<Drain<'_, T, B>> {
+        self.deque.drain(count).map(Drain)
+    }
+
+    /// The number of additional items that can be pushed before the deque is
+    /// full.
+    pub fn spare_capacity(&self) -> usize {
+        self.deque.spare_capacity()
+    }
+
+    /// The number of items currently in the deque.
+    pub fn len(&self) -> usize {
+        self.deque.len()
+    }
+
+    /// Returns `true` if the deque currently holds no items.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T, B: Buffer<T>> Default for Worker<T, B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, B: Buffer<T>> fmt::Debug for Worker<T, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Worker").field("len", &self.len()).finish()
+    }
+}
+
+/// A handle that lets another thread steal items from a [`Worker`]'s deque.
+///
+/// A `Stealer` can be cloned and shared freely between threads.
+pub struct Stealer<T, B: Buffer<T>> {
+    deque: Arc<RawDeque<T, B>>,
+}
+
+impl<T, B: Buffer<T>> Stealer<T, B> {
+    /// Steals a single item, or an error if the deque is empty or the steal
+    /// lost a race with a concurrent operation.
+    pub fn steal(&self) -> Result<T, StealError> {
+        self.deque.steal_one()
+    }
+
+    /// Steals a batch of items, moving all but one of them into `dest` and
+    /// returning the last one directly along with the number moved.
+    ///
+    /// `count` is called with the number of items available to steal and
+    /// returns how many to take; the actual number taken is clamped to what
+    /// is available and to `dest`'s spare capacity, and is always at least
+    /// one.
+    pub fn steal_and_pop<DB: Buffer<T>>(
+        &self,
+        dest: &Worker<T, DB>,
+        count: impl FnOnce(usize) -> usize,
+    ) -> Result<(T, usize), StealError> {
+        self.deque.steal_many(&dest.deque, count)
+    }
+
+    /// Upper-bound estimate of the number of items in the deque.
+    pub fn len(&self) -> usize {
+        self.deque.len_estimate()
+    }
+
+    /// Returns `true` if the deque appeared empty at the time of the call.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T, B: Buffer<T>> Clone for Stealer<T, B> {
+    fn clone(&self) -> Self {
+        Stealer {
+            deque: self.deque.clone(),
+        }
+    }
+}
+
+impl<T, B: Buffer<T>> fmt::Debug for Stealer<T, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Stealer").field("len", &self.len()).finish()
+    }
+}
+
+/// Iterator returned by [`Worker::drain`].
+pub struct Drain<'a, T, B: Buffer<T>>(crate::raw::Drain<'a, T, B>);
+
+impl<'a, T, B: Buffer<T>> Iterator for Drain<'a, T, B> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.0.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<'a, T, B: Buffer<T>> ExactSizeIterator for Drain<'a, T, B> {}
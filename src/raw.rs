@@ -0,0 +1,288 @@
+//! Shared ring-buffer core used by both the [`lifo`](crate::lifo) and
+//! [`fifo`](crate::fifo) flavors of the deque.
+//!
+//! The owning thread exclusively manipulates `bottom`; stealers race each
+//! other for `top` via compare-and-swap, and the owner joins that race when
+//! it pops the last item (or, for the [`fifo`](crate::fifo) flavor, on every
+//! pop). This is the classic Chase-Lev work-stealing deque,
+//! parameterized over a fixed [`Buffer`] capacity.
+
+use std::mem::MaybeUninit;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use crate::{Buffer, PushError, StealError};
+
+/// The shared ring buffer and indices backing a worker/stealer pair.
+///
+/// `top` is only ever advanced, never decreased, and is contended by
+/// stealers. `bottom` is only ever written by the owning thread.
+pub(crate) struct RawDeque<T, B: Buffer<T>> {
+    buffer: B::Array,
+    top: AtomicUsize,
+    bottom: AtomicUsize,
+    /// Set for the lifetime of an in-progress `Drain`, so that stealers back
+    /// off with `StealError::Busy` instead of racing the owner's bulk removal.
+    draining: AtomicBool,
+}
+
+unsafe impl<T: Send, B: Buffer<T>> Send for RawDeque<T, B> {}
+unsafe impl<T: Send, B: Buffer<T>> Sync for RawDeque<T, B> {}
+
+impl<T, B: Buffer<T>> RawDeque<T, B> {
+    const MASK: usize = B::CAPACITY - 1;
+
+    pub(crate) fn new() -> Self {
+        assert!(
+            B::CAPACITY.is_power_of_two(),
+            "buffer capacity must be a power of two"
+        );
+        RawDeque {
+            buffer: B::new_array(),
+            top: AtomicUsize::new(0),
+            bottom: AtomicUsize::new(0),
+            draining: AtomicBool::new(false),
+        }
+    }
+
+    fn slot(&self, idx: usize) -> *mut MaybeUninit<T> {
+        self.buffer.as_ref()[idx & Self::MASK].get()
+    }
+
+    unsafe fn write(&self, idx: usize, value: T) {
+        (*self.slot(idx)).write(value);
+    }
+
+    unsafe fn read(&self, idx: usize) -> T {
+        (*self.slot(idx)).assume_init_read()
+    }
+
+    /// Number of occupied slots, from the owning thread's point of view.
+    ///
+    /// Exact: only the owner writes `bottom`, and a concurrent steal can only
+    /// ever shrink the range further, never grow it.
+    pub(crate) fn len(&self) -> usize {
+        let bottom = self.bottom.load(Ordering::Relaxed);
+        let top = self.top.load(Ordering::Acquire);
+        bottom.wrapping_sub(top)
+    }
+
+    /// Upper-bound estimate of the occupied slots, from a stealer's point of
+    /// view. A concurrent pop or steal can make the snapshot momentarily
+    /// inconsistent, so the result is clamped to zero rather than allowed to
+    /// wrap.
+    pub(crate) fn len_estimate(&self) -> usize {
+        let top = self.top.load(Ordering::Acquire);
+        let bottom = self.bottom.load(Ordering::Acquire);
+        (bottom.wrapping_sub(top) as isize).max(0) as usize
+    }
+
+    pub(crate) fn spare_capacity(&self) -> usize {
+        B::CAPACITY - self.len()
+    }
+
+    pub(crate) fn push(&self, value: T) -> Result<(), PushError<T>> {
+        let bottom = self.bottom.load(Ordering::Relaxed);
+        let top = self.top.load(Ordering::Acquire);
+        if bottom.wrapping_sub(top) >= B::CAPACITY {
+            return Err(PushError(value));
+        }
+        unsafe { self.write(bottom, value) };
+        self.bottom.store(bottom.wrapping_add(1), Ordering::Release);
+        Ok(())
+    }
+
+    /// Pop the most recently pushed item (the owner's "bottom" end).
+    pub(crate) fn pop_bottom(&self) -> Option<T> {
+        let bottom = self.bottom.load(Ordering::Relaxed);
+        let new_bottom = bottom.wrapping_sub(1);
+        self.bottom.store(new_bottom, Ordering::Relaxed);
+        std::sync::atomic::fence(Ordering::SeqCst);
+
+        let top = self.top.load(Ordering::Relaxed);
+        let size = new_bottom.wrapping_sub(top) as isize;
+        if size < 0 {
+            // Already empty: nothing was ever popped, restore `bottom`.
+            self.bottom.store(bottom, Ordering::Relaxed);
+            return None;
+        }
+
+        let value = unsafe { self.read(new_bottom) };
+        if size == 0 {
+            // Last item in the deque: race the stealers for it.
+            let won = self
+                .top
+                .compare_exchange(
+                    top,
+                    top.wrapping_add(1),
+                    Ordering::SeqCst,
+                    Ordering::Relaxed,
+                )
+                .is_ok();
+            self.bottom.store(bottom, Ordering::Relaxed);
+            if !won {
+                // Lost the race: a stealer already claimed this slot and
+                // will return this same item, so don't drop our copy of it.
+                std::mem::forget(value);
+                return None;
+            }
+        }
+        Some(value)
+    }
+
+    /// Claim exactly one item from the `top` end via CAS.
+    pub(crate) fn steal_one(&self) -> Result<T, StealError> {
+        if self.draining.load(Ordering::Acquire) {
+            return Err(StealError::Busy);
+        }
+        let top = self.top.load(Ordering::Acquire);
+        std::sync::atomic::fence(Ordering::SeqCst);
+        let bottom = self.bottom.load(Ordering::Acquire);
+        if bottom.wrapping_sub(top) as isize <= 0 {
+            return Err(StealError::Empty);
+        }
+        let value = unsafe { self.read(top) };
+        if self
+            .top
+            .compare_exchange(
+                top,
+                top.wrapping_add(1),
+                Ordering::SeqCst,
+                Ordering::Relaxed,
+            )
+            .is_ok()
+        {
+            Ok(value)
+        } else {
+            // Lost the race: this was a duplicate read of a slot the winner
+            // still logically owns, so don't drop it here.
+            std::mem::forget(value);
+            Err(StealError::Busy)
+        }
+    }
+
+    /// Claim a run of items from `top`, pushing all but one into `dest` and
+    /// returning the last one directly, mirroring `Stealer::steal_and_pop`.
+    pub(crate) fn steal_many<DB: Buffer<T>>(
+        &self,
+        dest: &RawDeque<T, DB>,
+        count: impl FnOnce(usize) -> usize,
+    ) -> Result<(T, usize), StealError> {
+        if self.draining.load(Ordering::Acquire) {
+            return Err(StealError::Busy);
+        }
+        let top = self.top.load(Ordering::Acquire);
+        std::sync::atomic::fence(Ordering::SeqCst);
+        let bottom = self.bottom.load(Ordering::Acquire);
+        let available = bottom.wrapping_sub(top) as isize;
+        if available <= 0 {
+            return Err(StealError::Empty);
+        }
+        let available = available as usize;
+        let n = count(available).max(1).min(available).min(1 + dest.spare_capacity());
+
+        // Read the run out before attempting to publish it: if we lose the
+        // race for `top` none of this was observed by anyone else. The
+        // `n - 1` items bound for `dest` are staged in a stack buffer sized
+        // like `dest`'s own ring buffer (always big enough, since `n - 1 <=
+        // dest.spare_capacity()`), so a lost race needs no heap cleanup; the
+        // last item is kept in a plain local instead of a one-element Vec.
+        let staging = DB::new_array();
+        for i in 0..n - 1 {
+            unsafe { (*staging.as_ref()[i].get()).write(self.read(top.wrapping_add(i))) };
+        }
+        let last = unsafe { self.read(top.wrapping_add(n - 1)) };
+
+        if self
+            .top
+            .compare_exchange(
+                top,
+                top.wrapping_add(n),
+                Ordering::SeqCst,
+                Ordering::Relaxed,
+            )
+            .is_err()
+        {
+            // Lost the race: the slots are still logically owned by whoever
+            // won. `staging` only ever had `MaybeUninit`s written into it,
+            // never read back out, so there's nothing live in it to drop;
+            // `last` is a duplicate read that must not be dropped either.
+            std::mem::forget(last);
+            return Err(StealError::Busy);
+        }
+
+        for i in 0..n - 1 {
+            let value = unsafe { (*staging.as_ref()[i].get()).assume_init_read() };
+            dest.push(value).ok().expect("reserved spare capacity");
+        }
+        Ok((last, n - 1))
+    }
+
+    /// Plan a bulk removal of up to `count(len)` items from the `top` end,
+    /// blocking concurrent steals until the returned iterator is exhausted or
+    /// dropped. Returns `None` if the deque is currently empty.
+    pub(crate) fn drain(&self, count: impl FnOnce(usize) -> usize) -> Option<Drain<'_, T, B>> {
+        let len = self.len();
+        if len == 0 {
+            return None;
+        }
+        let n = count(len).min(len);
+        if n == 0 {
+            return None;
+        }
+        self.draining.store(true, Ordering::Release);
+        Some(Drain {
+            deque: self,
+            remaining: n,
+        })
+    }
+}
+
+impl<T, B: Buffer<T>> Drop for RawDeque<T, B> {
+    fn drop(&mut self) {
+        let mut top = self.top.load(Ordering::Relaxed);
+        let bottom = self.bottom.load(Ordering::Relaxed);
+        while top != bottom {
+            unsafe { drop(self.read(top)) };
+            top = top.wrapping_add(1);
+        }
+    }
+}
+
+/// Iterator draining a contiguous run of items from the `top` end of a
+/// [`RawDeque`], owned exclusively by the worker thread for its lifetime.
+pub(crate) struct Drain<'a, T, B: Buffer<T>> {
+    deque: &'a RawDeque<T, B>,
+    remaining: usize,
+}
+
+impl<'a, T, B: Buffer<T>> Iterator for Drain<'a, T, B> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let top = self.deque.top.load(Ordering::Relaxed);
+        let value = unsafe { self.deque.read(top) };
+        self.deque.top.store(top.wrapping_add(1), Ordering::Relaxed);
+        self.remaining -= 1;
+        if self.remaining == 0 {
+            self.deque.draining.store(false, Ordering::Release);
+        }
+        Some(value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, T, B: Buffer<T>> ExactSizeIterator for Drain<'a, T, B> {}
+
+impl<'a, T, B: Buffer<T>> Drop for Drain<'a, T, B> {
+    fn drop(&mut self) {
+        // Discard any remaining planned items, then let stealers back in.
+        for _ in self.by_ref() {}
+        self.deque.draining.store(false, Ordering::Release);
+    }
+}
@@ -0,0 +1,340 @@
+//! A small, rayon-style fork/join thread pool built directly on top of the
+//! `lifo` deque and [`Injector`](crate::injector::Injector).
+//!
+//! Behind the `scheduler` feature, this turns the raw [`Worker`]/[`Stealer`]
+//! primitives into something a caller can run recursive divide-and-conquer
+//! workloads against without hand-rolling the steal loop themselves:
+//!
+//! ```ignore
+//! use st3::scheduler::{join, scope};
+//!
+//! fn sum(xs: &[u64]) -> u64 {
+//!     if xs.len() <= 1024 {
+//!         return xs.iter().sum();
+//!     }
+//!     let mid = xs.len() / 2;
+//!     let (a, b) = join(|| sum(&xs[..mid]), || sum(&xs[mid..]));
+//!     a + b
+//! }
+//! ```
+//!
+//! A pool of background threads is started lazily on first use, sized to
+//! [`std::thread::available_parallelism`]. Each one owns a [`Worker`] and
+//! holds a [`Stealer`] to every peer; when its own deque and every peer come
+//! up empty it falls back to the shared [`Injector`], and only parks once
+//! that is empty too.
+
+use std::any::Any;
+use std::cell::Cell;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
+
+use crate::injector::Injector;
+use crate::lifo::{Stealer, Worker};
+use crate::PushError;
+
+/// Block size used for every worker's deque and the shared injector. Picked
+/// to comfortably absorb a burst of `spawn` calls without overflowing.
+type BlockSize = crate::B256;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+struct LocalWorker {
+    worker: Worker<Job, BlockSize>,
+    /// Where the round-robin steal scan starts: just past the last victim a
+    /// steal succeeded from, so successive scans don't all begin at the same
+    /// victim.
+    next_victim: Cell<usize>,
+}
+
+thread_local! {
+    static CURRENT: Cell<Option<*const LocalWorker>> = const { Cell::new(None) };
+}
+
+fn current_local() -> Option<&'static LocalWorker> {
+    CURRENT.with(|cell| cell.get()).map(|ptr| unsafe { &*ptr })
+}
+
+struct Pool {
+    stealers: Arc<Vec<Stealer<Job, BlockSize>>>,
+    injector: Arc<Injector<Job, BlockSize>>,
+    parked: Arc<(Mutex<()>, Condvar)>,
+}
+
+fn pool() -> &'static Pool {
+    static POOL: OnceLock<Pool> = OnceLock::new();
+    POOL.get_or_init(|| {
+        let size = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+        let workers: Vec<Worker<Job, BlockSize>> = (0..size).map(|_| Worker::new()).collect();
+        let stealers = Arc::new(workers.iter().map(Worker::stealer).collect::<Vec<_>>());
+        let injector = Arc::new(Injector::new());
+        let parked = Arc::new((Mutex::new(()), Condvar::new()));
+
+        for (index, worker) in workers.into_iter().enumerate() {
+            let stealers = stealers.clone();
+            let injector = injector.clone();
+            let parked = parked.clone();
+            thread::Builder::new()
+                .name(format!("st3-scheduler-{index}"))
+                .spawn(move || worker_loop(index, worker, stealers, injector, parked))
+                .expect("failed to spawn scheduler worker thread");
+        }
+
+        Pool {
+            stealers,
+            injector,
+            parked,
+        }
+    })
+}
+
+fn worker_loop(
+    index: usize,
+    worker: Worker<Job, BlockSize>,
+    stealers: Arc<Vec<Stealer<Job, BlockSize>>>,
+    injector: Arc<Injector<Job, BlockSize>>,
+    parked: Arc<(Mutex<()>, Condvar)>,
+) {
+    let local = LocalWorker {
+        worker,
+        next_victim: Cell::new(index),
+    };
+    CURRENT.with(|cell| cell.set(Some(&local as *const LocalWorker)));
+
+    loop {
+        match find_any_job(&local, &stealers, &injector) {
+            Some(job) => job(),
+            None => {
+                let (lock, cvar) = &*parked;
+                let guard = lock.lock().unwrap();
+                let _ = cvar.wait_timeout(guard, Duration::from_millis(1));
+            }
+        }
+    }
+}
+
+/// Finds one job to run: the local deque first, then a round-robin scan of
+/// peers, then the shared injector. Shared between [`worker_loop`] and
+/// [`join`], which both need to make progress instead of just blocking.
+fn find_any_job(
+    local: &LocalWorker,
+    stealers: &[Stealer<Job, BlockSize>],
+    injector: &Injector<Job, BlockSize>,
+) -> Option<Job> {
+    if let Some(job) = local.worker.pop() {
+        return Some(job);
+    }
+    if let Some(job) = find_stolen_job(local, stealers, stealers.len()) {
+        return Some(job);
+    }
+    injector
+        .steal_batch_and_pop(&local.worker, |available| available.clamp(1, 32))
+        .ok()
+        .map(|(job, _)| job)
+}
+
+fn find_stolen_job(
+    local: &LocalWorker,
+    stealers: &[Stealer<Job, BlockSize>],
+    peers: usize,
+) -> Option<Job> {
+    let start = local.next_victim.get();
+    for offset in 0..peers {
+        let victim = (start + offset) % peers;
+        match stealers[victim].steal_and_pop(&local.worker, |available| available.div_ceil(2)) {
+            Ok((job, _)) => {
+                local.next_victim.set(victim.wrapping_add(1));
+                return Some(job);
+            }
+            Err(_) => continue,
+        }
+    }
+    None
+}
+
+/// Submits a job to be run on the pool: directly onto the calling thread's
+/// own deque if it is a pool worker, or into the shared injector otherwise.
+fn submit(job: Job) {
+    let job = match current_local() {
+        Some(local) => match local.worker.push(job) {
+            Ok(()) => None,
+            Err(PushError(job)) => Some(job),
+        },
+        None => Some(job),
+    };
+    if let Some(job) = job {
+        pool().injector.push(job);
+    }
+    let (lock, cvar) = &*pool().parked;
+    let _guard = lock.lock().unwrap();
+    cvar.notify_all();
+}
+
+struct ScopeInner {
+    outstanding: AtomicUsize,
+    panic: Mutex<Option<Box<dyn Any + Send>>>,
+    done: (Mutex<bool>, Condvar),
+}
+
+impl ScopeInner {
+    fn finish_one(&self) {
+        if self.outstanding.fetch_sub(1, Ordering::SeqCst) == 1 {
+            let (lock, cvar) = &self.done;
+            *lock.lock().unwrap() = true;
+            cvar.notify_all();
+        }
+    }
+}
+
+/// Handle used inside a [`scope`] to spawn additional work that the scope
+/// will wait for before returning.
+pub struct Scope {
+    inner: Arc<ScopeInner>,
+}
+
+impl Scope {
+    /// Spawns a closure to run on the pool. The enclosing [`scope`] call
+    /// will not return until it (and everything it transitively spawns)
+    /// completes.
+    pub fn spawn<F>(&self, f: F)
+    where
+        F: FnOnce(&Scope) + Send + 'static,
+    {
+        self.inner.outstanding.fetch_add(1, Ordering::SeqCst);
+        let inner = self.inner.clone();
+        submit(Box::new(move || {
+            let scope = Scope {
+                inner: inner.clone(),
+            };
+            if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(|| f(&scope))) {
+                let mut guard = inner.panic.lock().unwrap();
+                if guard.is_none() {
+                    *guard = Some(payload);
+                }
+            }
+            inner.finish_one();
+        }));
+    }
+}
+
+/// Runs `f`, giving it a [`Scope`] to spawn work on, and blocks until every
+/// closure spawned through that scope (directly or transitively) has
+/// completed. A panic in any of them is re-raised here.
+pub fn scope<F, R>(f: F) -> R
+where
+    F: FnOnce(&Scope) -> R,
+{
+    let inner = Arc::new(ScopeInner {
+        // The scope body itself holds one unit of "outstanding work" so that
+        // jobs finishing before `f` returns can't prematurely signal done.
+        outstanding: AtomicUsize::new(1),
+        panic: Mutex::new(None),
+        done: (Mutex::new(false), Condvar::new()),
+    });
+    let s = Scope {
+        inner: inner.clone(),
+    };
+
+    let result = f(&s);
+
+    if inner.outstanding.fetch_sub(1, Ordering::SeqCst) != 1 {
+        // Don't just block: the outstanding work may be sitting unstolen on
+        // our own deque (e.g. a nested scope on a single-worker pool, or
+        // every peer busy), in which case nothing else will ever run it. If
+        // we're a pool worker, help out with the same local-deque/steal/
+        // injector search the worker loop uses, same as `join`.
+        let (lock, cvar) = &inner.done;
+        let mut done = lock.lock().unwrap();
+        while !*done {
+            match current_local() {
+                Some(local) => {
+                    drop(done);
+                    let pool = pool();
+                    match find_any_job(local, &pool.stealers, &pool.injector) {
+                        Some(job) => job(),
+                        None => thread::yield_now(),
+                    }
+                    done = lock.lock().unwrap();
+                }
+                None => done = cvar.wait(done).unwrap(),
+            }
+        }
+    }
+
+    if let Some(payload) = inner.panic.lock().unwrap().take() {
+        panic::resume_unwind(payload);
+    }
+    result
+}
+
+/// Runs `a` and `b`, potentially on different pool threads, and returns both
+/// results once both have completed. A panic in either is re-raised here.
+pub fn join<A, B, RA, RB>(a: A, b: B) -> (RA, RB)
+where
+    A: FnOnce() -> RA + Send,
+    B: FnOnce() -> RB + Send,
+    RA: Send,
+    RB: Send,
+{
+    let slot: Arc<Mutex<Option<thread::Result<RB>>>> = Arc::new(Mutex::new(None));
+    let done = Arc::new((Mutex::new(false), Condvar::new()));
+
+    // SAFETY: the closure below is only ever run once, synchronously, by
+    // whichever thread pops or steals this job, and this function does not
+    // return until that has happened and `done` has been observed — so the
+    // borrows `b` holds never actually outlive this stack frame, even
+    // though we briefly claim they're `'static` to hand the job to the pool.
+    // This is the same technique `rayon::join` uses.
+    let job: Box<dyn FnOnce() + Send + '_> = Box::new({
+        let slot = slot.clone();
+        let done = done.clone();
+        move || {
+            let result = panic::catch_unwind(AssertUnwindSafe(b));
+            *slot.lock().unwrap() = Some(result);
+            let (lock, cvar) = &*done;
+            *lock.lock().unwrap() = true;
+            cvar.notify_all();
+        }
+    });
+    let job: Box<dyn FnOnce() + Send + 'static> =
+        unsafe { std::mem::transmute(job) };
+    submit(job);
+
+    // `a` must not be allowed to unwind past us directly: until `b`'s job has
+    // actually finished, it may still be referencing data `b` borrowed from
+    // this stack frame, so we have to observe `done` before propagating
+    // either side's panic.
+    let a_result = panic::catch_unwind(AssertUnwindSafe(a));
+
+    // Wait for `b`, but don't just block: `b`'s job may be sitting unstolen
+    // on our own deque (e.g. a single-worker pool, or every peer busy), in
+    // which case nothing else will ever run it. If we're a pool worker,
+    // help out with the same local-deque/steal/injector search the worker
+    // loop uses instead of only waiting on the condvar.
+    let (lock, cvar) = &*done;
+    let mut guard = lock.lock().unwrap();
+    while !*guard {
+        match current_local() {
+            Some(local) => {
+                drop(guard);
+                let pool = pool();
+                match find_any_job(local, &pool.stealers, &pool.injector) {
+                    Some(job) => job(),
+                    None => thread::yield_now(),
+                }
+                guard = lock.lock().unwrap();
+            }
+            None => guard = cvar.wait(guard).unwrap(),
+        }
+    }
+    drop(guard);
+
+    let b_result = slot.lock().unwrap().take().unwrap();
+    match (a_result, b_result) {
+        (Ok(a_result), Ok(b_result)) => (a_result, b_result),
+        (Err(payload), _) | (_, Err(payload)) => panic::resume_unwind(payload),
+    }
+}
@@ -2,7 +2,32 @@ use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::thread::spawn;
 
-use st3::{lifo, Buffer, StealError};
+use st3::injector::Injector;
+use st3::lifo::growable;
+#[cfg(feature = "scheduler")]
+use st3::scheduler::{join, scope};
+use st3::{fifo, lifo, Buffer, StealError};
+
+/// Wraps a value with a shared counter bumped on drop, used by the
+/// concurrent tests below to catch double-drops or leaks along the steal
+/// paths under test: every `Counted` constructed must be dropped exactly
+/// once.
+struct Counted<T> {
+    value: T,
+    drops: Arc<AtomicUsize>,
+}
+
+impl<T> Counted<T> {
+    fn new(value: T, drops: Arc<AtomicUsize>) -> Self {
+        Counted { value, drops }
+    }
+}
+
+impl<T> Drop for Counted<T> {
+    fn drop(&mut self) {
+        self.drops.fetch_add(1, Ordering::Relaxed);
+    }
+}
 
 // Rotate the internal ring buffer indices by `n`.
 fn lifo_rotate<T: Default + std::fmt::Debug, B: Buffer<T>>(worker: &lifo::Worker<T, B>, n: usize) {
@@ -15,6 +40,17 @@ fn lifo_rotate<T: Default + std::fmt::Debug, B: Buffer<T>>(worker: &lifo::Worker
     }
 }
 
+// Rotate the internal ring buffer indices by `n`.
+fn fifo_rotate<T: Default + std::fmt::Debug, B: Buffer<T>>(worker: &fifo::Worker<T, B>, n: usize) {
+    let stealer = worker.stealer();
+    let dummy_worker = fifo::Worker::<T, st3::B2>::new();
+
+    for _ in 0..n {
+        worker.push(T::default()).unwrap();
+        stealer.steal_and_pop(&dummy_worker, |_| 1).unwrap();
+    }
+}
+
 #[test]
 fn lifo_single_threaded_steal() {
     const ROTATIONS: &[usize] = if cfg!(miri) {
@@ -180,7 +216,7 @@ fn lifo_multi_threaded_steal() {
         let mut stats = vec![0; N];
         'outer: loop {
             for _ in 0..rng.rand_range(1..10) {
-                while let Err(_) = worker.push(i) {}
+                while worker.push(i).is_err() {}
                 i += 1;
                 if i == N {
                     break 'outer;
@@ -235,9 +271,364 @@ fn lifo_multi_threaded_steal() {
     stats.push(t2.join().unwrap());
     for i in 0..N {
         let mut count = 0;
-        for j in 0..stats.len() {
-            count += stats[j][i];
+        for s in &stats {
+            count += s[i];
+        }
+        assert_eq!(count, 1);
+    }
+}
+
+#[test]
+fn lifo_len_and_is_empty() {
+    let worker = lifo::Worker::<_, st3::B128>::new();
+    let stealer = worker.stealer();
+    assert!(worker.is_empty());
+    assert!(stealer.is_empty());
+    assert_eq!(worker.len(), 0);
+    assert_eq!(stealer.len(), 0);
+
+    worker.push(1).unwrap();
+    worker.push(2).unwrap();
+    assert!(!worker.is_empty());
+    assert!(!stealer.is_empty());
+    assert_eq!(worker.len(), 2);
+    assert_eq!(stealer.len(), 2);
+
+    // `Stealer::steal` claims a single item from the top, same end as a
+    // `steal_and_pop` of 1 but without needing a destination worker.
+    assert_eq!(stealer.steal(), Ok(1));
+    assert_eq!(worker.len(), 1);
+    assert_eq!(worker.pop(), Some(2));
+    assert!(worker.is_empty());
+    assert_eq!(stealer.steal(), Err(StealError::Empty));
+}
+
+#[test]
+fn fifo_single_threaded_steal() {
+    const ROTATIONS: &[usize] = if cfg!(miri) {
+        &[0]
+    } else {
+        &[0, 255, 256, 257, 65535, 65536, 65537]
+    };
+
+    for &rotation in ROTATIONS {
+        let worker1 = fifo::Worker::<_, st3::B128>::new();
+        let worker2 = fifo::Worker::<_, st3::B128>::new();
+        let stealer1 = worker1.stealer();
+        fifo_rotate(&worker1, rotation);
+        fifo_rotate(&worker2, rotation);
+
+        worker1.push(1).unwrap();
+        worker1.push(2).unwrap();
+        worker1.push(3).unwrap();
+        worker1.push(4).unwrap();
+
+        // Unlike lifo, the owner's `pop` contends with stealers for the same
+        // (oldest-first) end of the deque.
+        assert_eq!(worker1.pop(), Some(1));
+        assert_eq!(stealer1.steal_and_pop(&worker2, |_| 2), Ok((3, 1)));
+        assert_eq!(worker1.pop(), Some(4));
+        assert_eq!(worker1.pop(), None);
+        assert_eq!(worker2.pop(), Some(2));
+        assert_eq!(worker2.pop(), None);
+    }
+}
+
+#[test]
+fn fifo_len_and_is_empty() {
+    let worker = fifo::Worker::<_, st3::B128>::new();
+    let stealer = worker.stealer();
+    assert!(worker.is_empty());
+    assert!(stealer.is_empty());
+
+    worker.push(1).unwrap();
+    worker.push(2).unwrap();
+    assert_eq!(worker.len(), 2);
+    assert_eq!(stealer.len(), 2);
+
+    assert_eq!(stealer.steal(), Ok(1));
+    assert_eq!(worker.len(), 1);
+    assert_eq!(worker.pop(), Some(2));
+    assert!(worker.is_empty());
+}
+
+#[test]
+fn fifo_multi_threaded_steal() {
+    const N: usize = if cfg!(miri) { 200 } else { 4_000_000 };
+
+    let counter = Arc::new(AtomicUsize::new(0));
+    let worker = fifo::Worker::<_, st3::B128>::new();
+    let stealer = worker.stealer();
+
+    let counter0 = counter.clone();
+    let stealer1 = stealer.clone();
+    let counter1 = counter.clone();
+    let counter2 = counter;
+
+    let t0 = spawn(move || {
+        let mut i = 0;
+        let mut rng = oorandom::Rand32::new(0);
+        let mut stats = vec![0; N];
+        'outer: loop {
+            for _ in 0..rng.rand_range(1..10) {
+                while worker.push(i).is_err() {}
+                i += 1;
+                if i == N {
+                    break 'outer;
+                }
+            }
+            if let Some(j) = worker.pop() {
+                stats[j] += 1;
+                counter0.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        stats
+    });
+
+    fn steal_periodically(
+        stealer: fifo::Stealer<usize, st3::B128>,
+        counter: Arc<AtomicUsize>,
+        rng_seed: u64,
+    ) -> Vec<usize> {
+        let mut stats = vec![0; N];
+        let mut rng = oorandom::Rand32::new(rng_seed);
+        let dest_worker = fifo::Worker::<_, st3::B128>::new();
+
+        loop {
+            if let Ok((i, _)) =
+                stealer.steal_and_pop(&dest_worker, |m| rng.rand_range(0..(m + 1) as u32) as usize)
+            {
+                stats[i] += 1;
+                counter.fetch_add(1, Ordering::Relaxed);
+                while let Some(j) = dest_worker.pop() {
+                    stats[j] += 1;
+                    counter.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+            let count = counter.load(Ordering::Relaxed);
+            if count == N {
+                break;
+            }
+            assert!(count < N);
+        }
+
+        stats
+    }
+    let t1 = spawn(move || steal_periodically(stealer1, counter1, 1));
+    let t2 = spawn(move || steal_periodically(stealer, counter2, 2));
+    let stats = [t0.join().unwrap(), t1.join().unwrap(), t2.join().unwrap()];
+    for i in 0..N {
+        let mut count = 0;
+        for s in &stats {
+            count += s[i];
         }
         assert_eq!(count, 1);
     }
 }
+
+#[test]
+fn growable_multi_threaded_steal() {
+    // Small enough that pushing `N` items forces many resizes,
+    // exercising the epoch/generation reclamation of superseded buffers
+    // (see `lifo::growable`) under concurrent steals.
+    const N: usize = if cfg!(miri) { 200 } else { 200_000 };
+
+    let drops = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::new(AtomicUsize::new(0));
+    let worker = growable::Worker::<Counted<usize>>::new::<st3::B2>();
+    let stealer = worker.stealer();
+
+    let drops0 = drops.clone();
+    let counter0 = counter.clone();
+    let stealer1 = stealer.clone();
+    let counter1 = counter.clone();
+    let counter2 = counter;
+
+    let t0 = spawn(move || {
+        let mut i = 0;
+        let mut rng = oorandom::Rand32::new(0);
+        let mut stats = vec![0; N];
+        'outer: loop {
+            for _ in 0..rng.rand_range(1..10) {
+                worker.push(Counted::new(i, drops0.clone()));
+                i += 1;
+                if i == N {
+                    break 'outer;
+                }
+            }
+            if let Some(item) = worker.pop() {
+                stats[item.value] += 1;
+                counter0.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        stats
+    });
+
+    fn steal_periodically(
+        stealer: growable::Stealer<Counted<usize>>,
+        counter: Arc<AtomicUsize>,
+        rng_seed: u64,
+    ) -> Vec<usize> {
+        let mut stats = vec![0; N];
+        let mut rng = oorandom::Rand32::new(rng_seed);
+        let dest_worker = lifo::Worker::<_, st3::B128>::new();
+
+        loop {
+            if let Ok((item, _)) =
+                stealer.steal_and_pop(&dest_worker, |m| rng.rand_range(0..(m + 1) as u32) as usize)
+            {
+                stats[item.value] += 1;
+                counter.fetch_add(1, Ordering::Relaxed);
+                while let Some(item) = dest_worker.pop() {
+                    stats[item.value] += 1;
+                    counter.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+            let count = counter.load(Ordering::Relaxed);
+            if count == N {
+                break;
+            }
+            assert!(count < N);
+        }
+
+        stats
+    }
+    let t1 = spawn(move || steal_periodically(stealer1, counter1, 1));
+    let t2 = spawn(move || steal_periodically(stealer, counter2, 2));
+    let stats = [t0.join().unwrap(), t1.join().unwrap(), t2.join().unwrap()];
+    for i in 0..N {
+        let mut count = 0;
+        for s in &stats {
+            count += s[i];
+        }
+        assert_eq!(count, 1);
+    }
+
+    // Every `Counted` pushed above must have been dropped exactly once: a
+    // lower count would mean one leaked past reclamation, a higher count
+    // would mean one was dropped twice.
+    assert_eq!(drops.load(Ordering::Relaxed), N);
+}
+
+#[test]
+fn injector_multi_threaded_steal_batch() {
+    // `B4` keeps each block tiny, so `N` pushes span many blocks and the
+    // head repeatedly drains, unlinks, and retires one while the steal
+    // threads below are still concurrently reading from others.
+    const N: usize = if cfg!(miri) { 200 } else { 200_000 };
+
+    let drops = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::new(AtomicUsize::new(0));
+    let injector = Arc::new(Injector::<Counted<usize>, st3::B4>::new());
+
+    let drops0 = drops.clone();
+    let injector0 = injector.clone();
+    let t0 = spawn(move || {
+        for i in 0..N {
+            injector0.push(Counted::new(i, drops0.clone()));
+        }
+    });
+
+    fn steal_periodically(
+        injector: Arc<Injector<Counted<usize>, st3::B4>>,
+        counter: Arc<AtomicUsize>,
+        rng_seed: u64,
+    ) -> Vec<usize> {
+        let mut stats = vec![0; N];
+        let mut rng = oorandom::Rand32::new(rng_seed);
+        let dest_worker = lifo::Worker::<_, st3::B128>::new();
+
+        loop {
+            match injector
+                .steal_batch_and_pop(&dest_worker, |m| rng.rand_range(1..(m + 1) as u32) as usize)
+            {
+                Ok((item, _)) => {
+                    stats[item.value] += 1;
+                    counter.fetch_add(1, Ordering::Relaxed);
+                    while let Some(item) = dest_worker.pop() {
+                        stats[item.value] += 1;
+                        counter.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+                Err(StealError::Busy) => continue,
+                Err(StealError::Empty) => {
+                    if counter.load(Ordering::Relaxed) == N {
+                        break;
+                    }
+                }
+            }
+        }
+
+        stats
+    }
+    let injector1 = injector.clone();
+    let counter1 = counter.clone();
+    let injector2 = injector;
+    let counter2 = counter;
+    let t1 = spawn(move || steal_periodically(injector1, counter1, 1));
+    let t2 = spawn(move || steal_periodically(injector2, counter2, 2));
+
+    t0.join().unwrap();
+    let stats = [t1.join().unwrap(), t2.join().unwrap()];
+    for i in 0..N {
+        let mut count = 0;
+        for s in &stats {
+            count += s[i];
+        }
+        assert_eq!(count, 1);
+    }
+
+    assert_eq!(drops.load(Ordering::Relaxed), N);
+}
+
+#[cfg(feature = "scheduler")]
+#[test]
+fn scheduler_join_runs_both_closures() {
+    let (a, b) = join(|| 1 + 1, || 2 + 2);
+    assert_eq!(a, 2);
+    assert_eq!(b, 4);
+}
+
+#[cfg(feature = "scheduler")]
+#[test]
+fn scheduler_scope_waits_for_nested_spawns() {
+    let total = Arc::new(AtomicUsize::new(0));
+    scope(|s| {
+        for _ in 0..64 {
+            let total = total.clone();
+            s.spawn(move |s| {
+                total.fetch_add(1, Ordering::Relaxed);
+                // A spawn from inside a spawned job: `scope` must also wait
+                // for work spawned transitively through the `Scope` handle
+                // it passed down.
+                let total = total.clone();
+                s.spawn(move |_| {
+                    total.fetch_add(1, Ordering::Relaxed);
+                });
+            });
+        }
+    });
+    assert_eq!(total.load(Ordering::Relaxed), 128);
+}
+
+#[cfg(feature = "scheduler")]
+#[test]
+fn scheduler_join_propagates_panic() {
+    let outcome = std::panic::catch_unwind(|| join(|| panic!("boom from join side"), || 1 + 1));
+    let payload = outcome.expect_err("join should re-raise a panic from either side");
+    assert_eq!(payload.downcast_ref::<&str>(), Some(&"boom from join side"));
+}
+
+#[cfg(feature = "scheduler")]
+#[test]
+fn scheduler_scope_propagates_panic() {
+    let outcome = std::panic::catch_unwind(|| {
+        scope(|s| {
+            s.spawn(|_| panic!("boom from spawned closure"));
+        });
+    });
+    let payload = outcome.expect_err("scope should re-raise a panic from a spawned closure");
+    assert_eq!(payload.downcast_ref::<&str>(), Some(&"boom from spawned closure"));
+}